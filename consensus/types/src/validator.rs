@@ -2,20 +2,29 @@ use crate::{
     test_utils::TestRandom, Address, BeaconState, ChainSpec, Epoch, EthSpec, Hash256,
     PublicKeyBytes,
 };
+use rand::RngCore;
 use serde_derive::{Deserialize, Serialize};
-use ssz_derive::{Decode, Encode};
-use test_random_derive::TestRandom;
-use tree_hash_derive::TreeHash;
+use ssz::{Decode, DecodeError, Encode, SszDecoderBuilder, SszEncoder};
+use std::sync::Arc;
+use tree_hash::{MerkleHasher, PackedEncoding, TreeHash, TreeHashType};
 
-/// Information about a `BeaconChain` validator.
+/// The immutable portion of a `Validator` record.
 ///
-/// Spec v0.12.1
+/// The `pubkey` never changes after validator creation and the `withdrawal_credentials` change
+/// only through `Validator::change_withdrawal_credentials` (which copies the record first if it is
+/// shared), so they are held behind a shared `Arc` to avoid duplicating these heavy fields when
+/// `BeaconState` copies are structurally shared across slots.
 #[cfg_attr(feature = "arbitrary-fuzz", derive(arbitrary::Arbitrary))]
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode, TestRandom, TreeHash)]
-pub struct Validator {
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorImmutable {
     pub pubkey: PublicKeyBytes,
     pub withdrawal_credentials: Hash256,
-    #[serde(with = "eth2_serde_utils::quoted_u64")]
+}
+
+/// The frequently-mutated portion of a `Validator` record.
+#[cfg_attr(feature = "arbitrary-fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatorMutable {
     pub effective_balance: u64,
     pub slashed: bool,
     pub activation_eligibility_epoch: Epoch,
@@ -24,33 +33,92 @@ pub struct Validator {
     pub withdrawable_epoch: Epoch,
 }
 
+/// Information about a `BeaconChain` validator.
+///
+/// The immutable `pubkey` and `withdrawal_credentials` live behind a shared `Arc`, while the
+/// mutable balance/epoch fields are stored inline. SSZ and tree-hash output is identical to the
+/// flat eight-field container layout.
+///
+/// Spec v0.12.1
+#[cfg_attr(feature = "arbitrary-fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validator {
+    immutable: Arc<ValidatorImmutable>,
+    mutable: ValidatorMutable,
+}
+
 impl Validator {
+    /// The validator's BLS public key.
+    pub fn pubkey(&self) -> &PublicKeyBytes {
+        &self.immutable.pubkey
+    }
+
+    /// A reference to the shared handle of the immutable portion of the validator.
+    pub fn immutable(&self) -> &Arc<ValidatorImmutable> {
+        &self.immutable
+    }
+
+    /// The validator's withdrawal credentials.
+    pub fn withdrawal_credentials(&self) -> Hash256 {
+        self.immutable.withdrawal_credentials
+    }
+
+    /// The validator's effective balance, in gwei.
+    pub fn effective_balance(&self) -> u64 {
+        self.mutable.effective_balance
+    }
+
+    /// Whether the validator has been slashed.
+    pub fn slashed(&self) -> bool {
+        self.mutable.slashed
+    }
+
+    /// The epoch at which the validator became eligible for the activation queue.
+    pub fn activation_eligibility_epoch(&self) -> Epoch {
+        self.mutable.activation_eligibility_epoch
+    }
+
+    /// The epoch at which the validator was activated.
+    pub fn activation_epoch(&self) -> Epoch {
+        self.mutable.activation_epoch
+    }
+
+    /// The epoch at which the validator exited (or will exit).
+    pub fn exit_epoch(&self) -> Epoch {
+        self.mutable.exit_epoch
+    }
+
+    /// The epoch at which the validator becomes withdrawable.
+    pub fn withdrawable_epoch(&self) -> Epoch {
+        self.mutable.withdrawable_epoch
+    }
+
     /// Returns `true` if the validator is considered active at some epoch.
     pub fn is_active_at(&self, epoch: Epoch) -> bool {
-        self.activation_epoch <= epoch && epoch < self.exit_epoch
+        self.activation_epoch() <= epoch && epoch < self.exit_epoch()
     }
 
     /// Returns `true` if the validator is slashable at some epoch.
     pub fn is_slashable_at(&self, epoch: Epoch) -> bool {
-        !self.slashed && self.activation_epoch <= epoch && epoch < self.withdrawable_epoch
+        !self.slashed() && self.activation_epoch() <= epoch && epoch < self.withdrawable_epoch()
     }
 
     /// Returns `true` if the validator is considered exited at some epoch.
     pub fn is_exited_at(&self, epoch: Epoch) -> bool {
-        self.exit_epoch <= epoch
+        self.exit_epoch() <= epoch
     }
 
     /// Returns `true` if the validator is able to withdraw at some epoch.
     pub fn is_withdrawable_at(&self, epoch: Epoch) -> bool {
-        epoch >= self.withdrawable_epoch
+        epoch >= self.withdrawable_epoch()
     }
 
     /// Returns `true` if the validator is eligible to join the activation queue.
     ///
     /// Spec v0.12.1
     pub fn is_eligible_for_activation_queue(&self, spec: &ChainSpec) -> bool {
-        self.activation_eligibility_epoch == spec.far_future_epoch
-            && self.effective_balance == spec.max_effective_balance
+        self.activation_eligibility_epoch() == spec.far_future_epoch
+            && self.effective_balance() == spec.max_effective_balance
     }
 
     /// Returns `true` if the validator is eligible to be activated.
@@ -62,25 +130,51 @@ impl Validator {
         spec: &ChainSpec,
     ) -> bool {
         // Placement in queue is finalized
-        self.activation_eligibility_epoch <= state.finalized_checkpoint().epoch
+        self.activation_eligibility_epoch() <= state.finalized_checkpoint().epoch
         // Has not yet been activated
-        && self.activation_epoch == spec.far_future_epoch
+        && self.activation_epoch() == spec.far_future_epoch
     }
 
     /// Returns `true` if the validator has eth1 withdrawal credential
     pub fn has_eth1_withdrawal_credential(&self, spec: &ChainSpec) -> bool {
-        self.withdrawal_credentials
+        self.withdrawal_credentials()
             .as_bytes()
             .first()
             .map(|byte| *byte == spec.eth1_address_withdrawal_prefix_byte)
             .unwrap_or(false)
     }
 
+    /// Returns `true` if the validator has a 0x02 prefixed "compounding" withdrawal credential.
+    pub fn has_compounding_withdrawal_credential(&self, spec: &ChainSpec) -> bool {
+        self.withdrawal_credentials()
+            .as_bytes()
+            .first()
+            .map(|byte| *byte == spec.compounding_withdrawal_prefix_byte)
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if the validator has an execution withdrawal credential (either the 0x01
+    /// eth1 prefix or the 0x02 compounding prefix).
+    pub fn has_execution_withdrawal_credential(&self, spec: &ChainSpec) -> bool {
+        self.has_compounding_withdrawal_credential(spec)
+            || self.has_eth1_withdrawal_credential(spec)
+    }
+
+    /// Returns the maximum effective balance for this validator, depending on whether it has a
+    /// compounding withdrawal credential.
+    pub fn get_max_effective_balance(&self, spec: &ChainSpec) -> u64 {
+        if self.has_compounding_withdrawal_credential(spec) {
+            spec.max_effective_balance_electra
+        } else {
+            spec.min_activation_balance
+        }
+    }
+
     /// Get the eth1 withdrawal address if this validator has one initialized.
     pub fn get_eth1_withdrawal_address(&self, spec: &ChainSpec) -> Option<Address> {
         self.has_eth1_withdrawal_credential(spec)
             .then(|| {
-                self.withdrawal_credentials
+                self.withdrawal_credentials()
                     .as_bytes()
                     .get(12..)
                     .map(Address::from_slice)
@@ -95,19 +189,336 @@ impl Validator {
         let mut bytes = [0u8; 32];
         bytes[0] = spec.eth1_address_withdrawal_prefix_byte;
         bytes[12..].copy_from_slice(execution_address.as_bytes());
-        self.withdrawal_credentials = Hash256::from(bytes);
+        Arc::make_mut(&mut self.immutable).withdrawal_credentials = Hash256::from(bytes);
     }
 
     /// Returns `true` if the validator is fully withdrawable at some epoch
     pub fn is_fully_withdrawable_at(&self, balance: u64, epoch: Epoch, spec: &ChainSpec) -> bool {
-        self.has_eth1_withdrawal_credential(spec) && self.withdrawable_epoch <= epoch && balance > 0
+        self.has_execution_withdrawal_credential(spec)
+            && self.withdrawable_epoch() <= epoch
+            && balance > 0
     }
 
     /// Returns `true` if the validator is partially withdrawable
     pub fn is_partially_withdrawable_validator(&self, balance: u64, spec: &ChainSpec) -> bool {
-        self.has_eth1_withdrawal_credential(spec)
-            && self.effective_balance == spec.max_effective_balance
-            && balance > spec.max_effective_balance
+        let max_effective_balance = self.get_max_effective_balance(spec);
+        self.has_execution_withdrawal_credential(spec)
+            && self.effective_balance() == max_effective_balance
+            && balance > max_effective_balance
+    }
+
+    /// Returns the standardized status of this validator, as exposed by the beacon node HTTP API.
+    ///
+    /// See the `/eth/v1/beacon/states/{state_id}/validators` endpoint for the meaning of each
+    /// status.
+    pub fn status(
+        &self,
+        current_epoch: Epoch,
+        balance: u64,
+        spec: &ChainSpec,
+    ) -> ValidatorStatus {
+        if self.activation_epoch() > current_epoch {
+            // Pending.
+            if self.activation_eligibility_epoch() == spec.far_future_epoch {
+                ValidatorStatus::PendingInitialized
+            } else {
+                ValidatorStatus::PendingQueued
+            }
+        } else if self.activation_epoch() <= current_epoch && current_epoch < self.exit_epoch() {
+            // Active.
+            if self.exit_epoch() == spec.far_future_epoch {
+                ValidatorStatus::ActiveOngoing
+            } else if self.slashed() {
+                ValidatorStatus::ActiveSlashed
+            } else {
+                ValidatorStatus::ActiveExiting
+            }
+        } else if self.exit_epoch() <= current_epoch && current_epoch < self.withdrawable_epoch() {
+            // Exited.
+            if self.slashed() {
+                ValidatorStatus::ExitedSlashed
+            } else {
+                ValidatorStatus::ExitedUnslashed
+            }
+        } else if self.withdrawable_epoch() <= current_epoch {
+            // Withdrawal.
+            if balance != 0 {
+                ValidatorStatus::WithdrawalPossible
+            } else {
+                ValidatorStatus::WithdrawalDone
+            }
+        } else {
+            // This path is unreachable given a well-formed validator, but default to the most
+            // conservative status rather than panicking.
+            ValidatorStatus::PendingInitialized
+        }
+    }
+}
+
+/// The standardized status strings exposed by the beacon node HTTP API.
+#[cfg_attr(feature = "arbitrary-fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    PendingInitialized,
+    PendingQueued,
+    ActiveOngoing,
+    ActiveExiting,
+    ActiveSlashed,
+    ExitedUnslashed,
+    ExitedSlashed,
+    WithdrawalPossible,
+    WithdrawalDone,
+    Active,
+    Pending,
+    Exited,
+    Withdrawal,
+}
+
+impl ValidatorStatus {
+    /// Returns the coarse status group (`Pending`, `Active`, `Exited` or `Withdrawal`) that this
+    /// status belongs to.
+    pub fn superstatus(&self) -> Self {
+        match self {
+            ValidatorStatus::PendingInitialized | ValidatorStatus::PendingQueued => {
+                ValidatorStatus::Pending
+            }
+            ValidatorStatus::ActiveOngoing
+            | ValidatorStatus::ActiveExiting
+            | ValidatorStatus::ActiveSlashed => ValidatorStatus::Active,
+            ValidatorStatus::ExitedUnslashed | ValidatorStatus::ExitedSlashed => {
+                ValidatorStatus::Exited
+            }
+            ValidatorStatus::WithdrawalPossible | ValidatorStatus::WithdrawalDone => {
+                ValidatorStatus::Withdrawal
+            }
+            _ => *self,
+        }
+    }
+
+    /// Returns `true` if this status matches `filter`, where `filter` may be either a specific
+    /// status or one of the coarse groups returned by `superstatus`.
+    pub fn matches_filter(&self, filter: &ValidatorStatus) -> bool {
+        if self == filter {
+            true
+        } else {
+            self.superstatus() == *filter
+        }
+    }
+}
+
+/// A flat view over `Validator` used to preserve the wire and JSON representation of the original
+/// eight-field container.
+#[cfg_attr(feature = "arbitrary-fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ValidatorSerde {
+    pubkey: PublicKeyBytes,
+    withdrawal_credentials: Hash256,
+    #[serde(with = "eth2_serde_utils::quoted_u64")]
+    effective_balance: u64,
+    slashed: bool,
+    activation_eligibility_epoch: Epoch,
+    activation_epoch: Epoch,
+    exit_epoch: Epoch,
+    withdrawable_epoch: Epoch,
+}
+
+impl From<&Validator> for ValidatorSerde {
+    fn from(validator: &Validator) -> Self {
+        ValidatorSerde {
+            pubkey: validator.immutable.pubkey,
+            withdrawal_credentials: validator.immutable.withdrawal_credentials,
+            effective_balance: validator.mutable.effective_balance,
+            slashed: validator.mutable.slashed,
+            activation_eligibility_epoch: validator.mutable.activation_eligibility_epoch,
+            activation_epoch: validator.mutable.activation_epoch,
+            exit_epoch: validator.mutable.exit_epoch,
+            withdrawable_epoch: validator.mutable.withdrawable_epoch,
+        }
+    }
+}
+
+impl From<ValidatorSerde> for Validator {
+    fn from(validator: ValidatorSerde) -> Self {
+        Validator {
+            immutable: Arc::new(ValidatorImmutable {
+                pubkey: validator.pubkey,
+                withdrawal_credentials: validator.withdrawal_credentials,
+            }),
+            mutable: ValidatorMutable {
+                effective_balance: validator.effective_balance,
+                slashed: validator.slashed,
+                activation_eligibility_epoch: validator.activation_eligibility_epoch,
+                activation_epoch: validator.activation_epoch,
+                exit_epoch: validator.exit_epoch,
+                withdrawable_epoch: validator.withdrawable_epoch,
+            },
+        }
+    }
+}
+
+impl serde::Serialize for Validator {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        ValidatorSerde::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Validator {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        ValidatorSerde::deserialize(deserializer).map(Validator::from)
+    }
+}
+
+/// The SSZ fixed length of a `Validator`, equal to the sum of its eight fixed-length fields.
+fn ssz_fixed_len() -> usize {
+    <PublicKeyBytes as Encode>::ssz_fixed_len()
+        + <Hash256 as Encode>::ssz_fixed_len()
+        + <u64 as Encode>::ssz_fixed_len()
+        + <bool as Encode>::ssz_fixed_len()
+        + <Epoch as Encode>::ssz_fixed_len() * 4
+}
+
+impl Encode for Validator {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        ssz_fixed_len()
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        ssz_fixed_len()
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let offset = ssz_fixed_len();
+        let mut encoder = SszEncoder::container(buf, offset);
+        encoder.append(&self.immutable.pubkey);
+        encoder.append(&self.immutable.withdrawal_credentials);
+        encoder.append(&self.mutable.effective_balance);
+        encoder.append(&self.mutable.slashed);
+        encoder.append(&self.mutable.activation_eligibility_epoch);
+        encoder.append(&self.mutable.activation_epoch);
+        encoder.append(&self.mutable.exit_epoch);
+        encoder.append(&self.mutable.withdrawable_epoch);
+        encoder.finalize();
+    }
+}
+
+impl Decode for Validator {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        ssz_fixed_len()
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut builder = SszDecoderBuilder::new(bytes);
+        builder.register_type::<PublicKeyBytes>()?;
+        builder.register_type::<Hash256>()?;
+        builder.register_type::<u64>()?;
+        builder.register_type::<bool>()?;
+        builder.register_type::<Epoch>()?;
+        builder.register_type::<Epoch>()?;
+        builder.register_type::<Epoch>()?;
+        builder.register_type::<Epoch>()?;
+
+        let mut decoder = builder.build()?;
+
+        Ok(Validator {
+            immutable: Arc::new(ValidatorImmutable {
+                pubkey: decoder.decode_next()?,
+                withdrawal_credentials: decoder.decode_next()?,
+            }),
+            mutable: ValidatorMutable {
+                effective_balance: decoder.decode_next()?,
+                slashed: decoder.decode_next()?,
+                activation_eligibility_epoch: decoder.decode_next()?,
+                activation_epoch: decoder.decode_next()?,
+                exit_epoch: decoder.decode_next()?,
+                withdrawable_epoch: decoder.decode_next()?,
+            },
+        })
+    }
+}
+
+impl TreeHash for Validator {
+    fn tree_hash_type() -> TreeHashType {
+        TreeHashType::Container
+    }
+
+    fn tree_hash_packed_encoding(&self) -> PackedEncoding {
+        unreachable!("Struct should never be packed.")
+    }
+
+    fn tree_hash_packing_factor() -> usize {
+        unreachable!("Struct should never be packed.")
+    }
+
+    fn tree_hash_root(&self) -> Hash256 {
+        let mut hasher = MerkleHasher::with_leaves(8);
+
+        hasher
+            .write(self.immutable.pubkey.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(self.immutable.withdrawal_credentials.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(self.mutable.effective_balance.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(self.mutable.slashed.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(
+                self.mutable
+                    .activation_eligibility_epoch
+                    .tree_hash_root()
+                    .as_bytes(),
+            )
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(self.mutable.activation_epoch.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(self.mutable.exit_epoch.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+        hasher
+            .write(self.mutable.withdrawable_epoch.tree_hash_root().as_bytes())
+            .expect("tree hash derive should not apply too many leaves");
+
+        hasher
+            .finish()
+            .expect("tree hash derive should not have a remaining buffer")
+    }
+}
+
+impl TestRandom for Validator {
+    fn random_for_test(rng: &mut impl RngCore) -> Self {
+        Validator {
+            immutable: Arc::new(ValidatorImmutable {
+                pubkey: PublicKeyBytes::random_for_test(rng),
+                withdrawal_credentials: Hash256::random_for_test(rng),
+            }),
+            mutable: ValidatorMutable {
+                effective_balance: u64::random_for_test(rng),
+                slashed: bool::random_for_test(rng),
+                activation_eligibility_epoch: Epoch::random_for_test(rng),
+                activation_epoch: Epoch::random_for_test(rng),
+                exit_epoch: Epoch::random_for_test(rng),
+                withdrawable_epoch: Epoch::random_for_test(rng),
+            },
+        }
     }
 }
 
@@ -115,14 +526,18 @@ impl Default for Validator {
     /// Yields a "default" `Validator`. Primarily used for testing.
     fn default() -> Self {
         Self {
-            pubkey: PublicKeyBytes::empty(),
-            withdrawal_credentials: Hash256::default(),
-            activation_eligibility_epoch: Epoch::from(std::u64::MAX),
-            activation_epoch: Epoch::from(std::u64::MAX),
-            exit_epoch: Epoch::from(std::u64::MAX),
-            withdrawable_epoch: Epoch::from(std::u64::MAX),
-            slashed: false,
-            effective_balance: std::u64::MAX,
+            immutable: Arc::new(ValidatorImmutable {
+                pubkey: PublicKeyBytes::empty(),
+                withdrawal_credentials: Hash256::default(),
+            }),
+            mutable: ValidatorMutable {
+                activation_eligibility_epoch: Epoch::from(std::u64::MAX),
+                activation_epoch: Epoch::from(std::u64::MAX),
+                exit_epoch: Epoch::from(std::u64::MAX),
+                withdrawable_epoch: Epoch::from(std::u64::MAX),
+                slashed: false,
+                effective_balance: std::u64::MAX,
+            },
         }
     }
 }
@@ -140,17 +555,15 @@ mod tests {
         assert!(!v.is_active_at(epoch));
         assert!(!v.is_exited_at(epoch));
         assert!(!v.is_withdrawable_at(epoch));
-        assert!(!v.slashed);
+        assert!(!v.slashed());
     }
 
     #[test]
     fn is_active_at() {
         let epoch = Epoch::new(10);
 
-        let v = Validator {
-            activation_epoch: epoch,
-            ..Validator::default()
-        };
+        let mut v = Validator::default();
+        v.mutable.activation_epoch = epoch;
 
         assert!(!v.is_active_at(epoch - 1));
         assert!(v.is_active_at(epoch));
@@ -161,10 +574,8 @@ mod tests {
     fn is_exited_at() {
         let epoch = Epoch::new(10);
 
-        let v = Validator {
-            exit_epoch: epoch,
-            ..Validator::default()
-        };
+        let mut v = Validator::default();
+        v.mutable.exit_epoch = epoch;
 
         assert!(!v.is_exited_at(epoch - 1));
         assert!(v.is_exited_at(epoch));
@@ -175,10 +586,8 @@ mod tests {
     fn is_withdrawable_at() {
         let epoch = Epoch::new(10);
 
-        let v = Validator {
-            withdrawable_epoch: epoch,
-            ..Validator::default()
-        };
+        let mut v = Validator::default();
+        v.mutable.withdrawable_epoch = epoch;
 
         assert!(!v.is_withdrawable_at(epoch - 1));
         assert!(v.is_withdrawable_at(epoch));